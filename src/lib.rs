@@ -15,17 +15,28 @@ NOTES:
   - To prevent the deployed contract from being modified or deleted, it should not have any access
     keys on its account.
 */
+use near_contract_standards::fungible_token::core::FungibleTokenCore;
+use near_contract_standards::fungible_token::events::{FtBurn, FtMint};
 use near_contract_standards::fungible_token::metadata::{
     FungibleTokenMetadata, FungibleTokenMetadataProvider, FT_METADATA_SPEC,
 };
+use near_contract_standards::fungible_token::resolver::FungibleTokenResolver;
 use near_contract_standards::fungible_token::FungibleToken;
+use near_sdk::assert_one_yocto;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::LazyOption;
+use near_sdk::collections::LookupMap;
 use near_sdk::collections::LookupSet;
 use near_sdk::json_types::U128;
 use near_sdk::serde_json::json;
 use near_sdk::{env, ext_contract, log, near_bindgen, AccountId, Balance, PanicOnDefault, Promise, PromiseOrValue, PromiseResult, Gas};
 
+/// A set of ACL roles packed into a bitmask, the way `acl_grant_role`/`acl_has_role` store them
+/// per account. Roles are additive, so an account can hold any combination at once.
+pub type Role = u8;
+pub const WHITELIST_MANAGER: Role = 0b01;
+pub const PAUSER: Role = 0b10;
+
 fn is_promise_success() -> bool {
     assert_eq!(
         env::promise_results_count(),
@@ -45,27 +56,60 @@ pub struct Contract {
     token: FungibleToken,
     metadata: LazyOption<FungibleTokenMetadata>,
     factory_whitelist: LookupSet<AccountId>,
+    roles: LookupMap<AccountId, Role>,
+    paused: bool,
+    soulbound: bool,
 }
 
 const GAS_FOR_FT_TRANSFER_CALL: Gas = Gas(60_000_000_000_000);
 const GAS_FOR_ADD_WHITELIST_CALL: Gas = Gas(30_000_000_000_000);
+const GAS_FOR_MIGRATE_CALL: Gas = Gas(20_000_000_000_000);
+const GAS_FOR_RESOLVE_BATCH_CALL: Gas = Gas(30_000_000_000_000);
+/// Floor below which an `ft_transfer` leg of `transfer_batch` isn't worth attempting.
+const MIN_GAS_PER_BATCH_TRANSFER: Gas = Gas(15_000_000_000_000);
+/// NEAR caps a function call's prepaid gas at 300 TGas, so a batch has to stay small enough
+/// that `prepaid_gas / transfers.len()` still clears `MIN_GAS_PER_BATCH_TRANSFER` per leg.
+const MAX_TRANSFER_BATCH_SIZE: usize = 15;
 const DATA_IMAGE_SVG_NEAR_ICON: &str = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 288 288'%3E%3Cg id='l' data-name='l'%3E%3Cpath d='M187.58,79.81l-30.1,44.69a3.2,3.2,0,0,0,4.75,4.2L191.86,103a1.2,1.2,0,0,1,2,.91v80.46a1.2,1.2,0,0,1-2.12.77L102.18,77.93A15.35,15.35,0,0,0,90.47,72.5H87.34A15.34,15.34,0,0,0,72,87.84V201.16A15.34,15.34,0,0,0,87.34,216.5h0a15.35,15.35,0,0,0,13.08-7.31l30.1-44.69a3.2,3.2,0,0,0-4.75-4.2L96.14,186a1.2,1.2,0,0,1-2-.91V104.61a1.2,1.2,0,0,1,2.12-.77l89.55,107.23a15.35,15.35,0,0,0,11.71,5.43h3.13A15.34,15.34,0,0,0,216,201.16V87.84A15.34,15.34,0,0,0,200.66,72.5h0A15.35,15.35,0,0,0,187.58,79.81Z'/%3E%3C/g%3E%3C/svg%3E";
 
 /// Indicates there are no deposit for a callback for better readability.
 const NO_DEPOSIT: u128 = 0;
 
+/// `transfer_with_reference` requires a fixed-length hex reference, matching the digest
+/// length of a sha256 hash, so off-chain accounting systems can key on it reliably.
+const PAYMENT_REFERENCE_LENGTH: usize = 64;
+
 #[ext_contract(ext_whitelist)]
 pub trait ExtWhitelist {
     /// Callback after creating account and claiming linkdrop.
     fn add_whitelist(&mut self, account_id: AccountId) -> bool;
 }
 
+#[ext_contract(ext_self)]
+pub trait ExtSelf {
+    /// Callback after a `transfer_batch` batch of `ft_transfer`s has all settled.
+    fn resolve_batch(&mut self, receivers: Vec<AccountId>) -> Vec<bool>;
+}
+
+/// Lets a contract run custom logic right after its code has been swapped in by `upgrade()`,
+/// before the new state is handed back to the runtime. Modeled on the Upgrade/UpgradeHook
+/// traits from near-sdk-contract-tools.
+pub trait UpgradeHook {
+    fn on_upgrade(&mut self);
+}
+
+impl UpgradeHook for Contract {
+    fn on_upgrade(&mut self) {
+        log!("Contract migrated to a new version");
+    }
+}
+
 #[near_bindgen]
 impl Contract {
     /// Initializes the contract with the given total supply owned by the given `owner_id` with
     /// default metadata (for example purposes only).
     #[init]
-    pub fn new_default_meta(owner_id: AccountId, total_supply: U128) -> Self {
+    pub fn new_default_meta(owner_id: AccountId, total_supply: U128, soulbound: bool) -> Self {
         Self::new(
             owner_id,
             total_supply,
@@ -78,28 +122,39 @@ impl Contract {
                 reference_hash: None,
                 decimals: 24,
             },
+            soulbound,
         )
     }
 
     /// Initializes the contract with the given total supply owned by the given `owner_id` with
-    /// the given fungible token metadata.
+    /// the given fungible token metadata. When `soulbound` is set, the issued tokens become a
+    /// non-transferable badge: only the owner's `mint`/`revoke` can move balances, and
+    /// `factory_whitelist` doubles as the set of current badge holders.
     #[init]
     pub fn new(
         owner_id: AccountId,
         total_supply: U128,
         metadata: FungibleTokenMetadata,
+        soulbound: bool,
     ) -> Self {
         assert!(!env::state_exists(), "Already initialized");
         metadata.assert_valid();
         let mut this = Self {
-            owner_id,
+            owner_id: owner_id.clone(),
             token: FungibleToken::new(b"a".to_vec()),
             metadata: LazyOption::new(b"m".to_vec(), Some(&metadata)),
             factory_whitelist: LookupSet::new(b"f".to_vec()),
+            roles: LookupMap::new(b"r".to_vec()),
+            paused: false,
+            soulbound,
         };
+        this.roles.insert(&owner_id, &(WHITELIST_MANAGER | PAUSER));
         this.token.internal_register_account(&this.owner_id);
         this.token.internal_deposit(&this.owner_id, total_supply.into());
-        near_contract_standards::fungible_token::events::FtMint {
+        if this.soulbound {
+            this.factory_whitelist.insert(&this.owner_id);
+        }
+        FtMint {
             owner_id: &this.owner_id,
             amount: &total_supply,
             memo: Some("Initial tokens supply is minted"),
@@ -118,6 +173,7 @@ impl Contract {
             env::is_valid_account_id(receiver_id.as_bytes()),
             "Invalid account id"
         );
+        self.assert_not_paused();
 
         log!("Prepaid gas - {}", format!("{:?}", env::prepaid_gas()));
         log!("Used gas - {}", format!("{:?}", env::used_gas()));
@@ -140,6 +196,141 @@ impl Contract {
             GAS_FOR_ADD_WHITELIST_CALL,))
     }
 
+    /// Transfers `amount` to `receiver_id` and routes `fee_amount` to `fee_address` in the same
+    /// call, tagging both legs with an opaque `payment_reference` so off-chain invoicing systems
+    /// can match the resulting event back to an invoice. Borrows the fungible-proxy pattern.
+    /// Both legs are validated against the sender's balance up front, so a call that would
+    /// underflow either leg panics before any state changes.
+    pub fn transfer_with_reference(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        payment_reference: String,
+        fee_amount: U128,
+        fee_address: AccountId,
+    ) {
+        assert!(!self.soulbound, "token is soulbound");
+        self.assert_not_paused();
+        assert_eq!(
+            payment_reference.len(),
+            PAYMENT_REFERENCE_LENGTH,
+            "payment_reference must be a {}-character hex string",
+            PAYMENT_REFERENCE_LENGTH
+        );
+        assert!(
+            payment_reference.chars().all(|c| c.is_ascii_hexdigit()),
+            "payment_reference must be a hex string"
+        );
+
+        let sender_id = env::predecessor_account_id();
+        let total = amount
+            .0
+            .checked_add(fee_amount.0)
+            .expect("Total amount overflow");
+        assert!(
+            self.token.ft_balance_of(sender_id.clone()).0 >= total,
+            "The account doesn't have enough balance to cover the transfer and the fee"
+        );
+
+        self.token
+            .internal_transfer(&sender_id, &receiver_id, amount.0, None);
+        if fee_amount.0 > 0 {
+            self.token
+                .internal_transfer(&sender_id, &fee_address, fee_amount.0, None);
+        }
+
+        log!(
+            "EVENT_JSON:{}",
+            json!({
+                "standard": "nep141",
+                "version": "1.0.0",
+                "event": "transfer_with_reference",
+                "data": [{
+                    "sender_id": sender_id,
+                    "receiver_id": receiver_id,
+                    "amount": amount,
+                    "payment_reference": payment_reference,
+                    "fee_amount": fee_amount,
+                    "fee_address": fee_address,
+                }]
+            })
+        );
+    }
+
+    /// Fans out one `ft_transfer` per `(receiver_id, amount)` pair and joins them with the
+    /// NEP-264 `Promise::and` API into a single `resolve_batch` callback, instead of the single
+    /// `.then()` chain `transfer()` uses. Lets a factory onboard many accounts in one
+    /// transaction, whitelisting only the receivers whose transfer actually succeeded.
+    pub fn transfer_batch(&mut self, transfers: Vec<(AccountId, U128)>) -> Promise {
+        self.assert_not_paused();
+        assert!(!transfers.is_empty(), "transfers must not be empty");
+        assert!(
+            transfers.len() <= MAX_TRANSFER_BATCH_SIZE,
+            "transfer_batch supports at most {} transfers per call",
+            MAX_TRANSFER_BATCH_SIZE
+        );
+        let receivers: Vec<AccountId> = transfers.iter().map(|(account_id, _)| account_id.clone()).collect();
+
+        // Split whatever gas is left (after reserving the resolve_batch callback) evenly across
+        // the legs, instead of attaching a flat per-leg constant that would blow past NEAR's
+        // 300 TGas cap once enough transfers are batched together.
+        let gas_per_transfer = Gas(
+            env::prepaid_gas()
+                .0
+                .saturating_sub(env::used_gas().0)
+                .saturating_sub(GAS_FOR_RESOLVE_BATCH_CALL.0)
+                / transfers.len() as u64,
+        );
+        assert!(
+            gas_per_transfer >= MIN_GAS_PER_BATCH_TRANSFER,
+            "Not enough gas attached to cover {} transfers",
+            transfers.len()
+        );
+
+        let mut legs = transfers.into_iter().map(|(receiver_id, amount)| {
+            Promise::new(self.owner_id.clone()).function_call(
+                "ft_transfer".to_string(),
+                json!({
+                    "receiver_id": receiver_id,
+                    "amount": amount
+                })
+                .to_string()
+                .into_bytes(),
+                1,
+                gas_per_transfer,
+            )
+        });
+        let joined = legs.next().unwrap();
+        let joined = legs.fold(joined, Promise::and);
+
+        joined.then(ext_self::resolve_batch(
+            receivers,
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_BATCH_CALL,
+        ))
+    }
+
+    #[private]
+    pub fn resolve_batch(&mut self, receivers: Vec<AccountId>) -> Vec<bool> {
+        assert_eq!(
+            env::promise_results_count() as usize,
+            receivers.len(),
+            "Promise results count mismatch"
+        );
+        receivers
+            .into_iter()
+            .enumerate()
+            .map(|(i, receiver_id)| {
+                let success = matches!(env::promise_result(i as u64), PromiseResult::Successful(_));
+                if success {
+                    self.factory_whitelist.insert(&receiver_id);
+                }
+                success
+            })
+            .collect()
+    }
+
     fn on_account_closed(&mut self, account_id: AccountId, balance: Balance) {
         log!("Closed @{} with {}", account_id, balance);
     }
@@ -172,9 +363,244 @@ impl Contract {
         );
         self.factory_whitelist.contains(&account_id)
     }
+
+    fn assert_owner(&self) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only the owner can call this method"
+        );
+    }
+
+    fn assert_role(&self, role: Role) {
+        assert!(
+            self.acl_has_role(role, env::predecessor_account_id()),
+            "Insufficient permissions"
+        );
+    }
+
+    fn assert_not_paused(&self) {
+        assert!(!self.paused, "Contract is paused");
+    }
+
+    /// Grants `role` to `account_id`, on top of whatever roles it already holds. Owner-only.
+    pub fn acl_grant_role(&mut self, role: Role, account_id: AccountId) {
+        self.assert_owner();
+        let roles = self.roles.get(&account_id).unwrap_or(0);
+        self.roles.insert(&account_id, &(roles | role));
+    }
+
+    /// Revokes `role` from `account_id`, leaving any other roles it holds untouched. Owner-only.
+    pub fn acl_revoke_role(&mut self, role: Role, account_id: AccountId) {
+        self.assert_owner();
+        let roles = self.roles.get(&account_id).unwrap_or(0);
+        self.roles.insert(&account_id, &(roles & !role));
+    }
+
+    pub fn acl_has_role(&self, role: Role, account_id: AccountId) -> bool {
+        self.roles.get(&account_id).unwrap_or(0) & role != 0
+    }
+
+    /// Callable by `WhitelistManager` holders, unlike the cross-contract `add_whitelist`
+    /// callback which only ever fires after a `transfer()`.
+    pub fn add_to_whitelist(&mut self, account_id: AccountId) {
+        self.assert_role(WHITELIST_MANAGER);
+        assert!(
+            env::is_valid_account_id(account_id.as_bytes()),
+            "Invalid account id"
+        );
+        self.factory_whitelist.insert(&account_id);
+    }
+
+    pub fn remove_from_whitelist(&mut self, account_id: AccountId) {
+        self.assert_role(WHITELIST_MANAGER);
+        self.factory_whitelist.remove(&account_id);
+    }
+
+    /// Emergency stop for transfers. Gated on `Pauser` so operators can react to a
+    /// compromised whitelist flow without needing the owner key.
+    pub fn pause(&mut self) {
+        self.assert_role(PAUSER);
+        self.paused = true;
+        log!("Contract paused");
+    }
+
+    pub fn unpause(&mut self) {
+        self.assert_role(PAUSER);
+        self.paused = false;
+        log!("Contract unpaused");
+    }
+
+    /// Mints tokens 1:1 for the attached NEAR deposit, modeled on the w-near wrapping
+    /// contracts. An unregistered caller is registered first, and the storage cost is taken out
+    /// of the attached deposit before the rest is minted. Disabled in soulbound mode, where
+    /// only the owner's `mint`/`revoke` may change balances.
+    #[payable]
+    pub fn near_deposit(&mut self) {
+        assert!(!self.soulbound, "token is soulbound");
+        let account_id = env::predecessor_account_id();
+        let mut amount = env::attached_deposit();
+        assert!(amount > 0, "Requires a positive attached deposit");
+
+        if self.storage_balance_of(account_id.clone()).is_none() {
+            let storage_cost = self.storage_balance_bounds().min.0;
+            assert!(
+                amount >= storage_cost,
+                "Attached deposit must cover the {} yoctoNEAR storage cost to register",
+                storage_cost
+            );
+            self.token.internal_register_account(&account_id);
+            amount -= storage_cost;
+        }
+
+        self.token.internal_deposit(&account_id, amount);
+        FtMint {
+            owner_id: &account_id,
+            amount: &amount.into(),
+            memo: Some("near_deposit"),
+        }
+        .emit();
+    }
+
+    /// Burns `amount` tokens from the caller and sends back the same amount of native NEAR.
+    #[payable]
+    pub fn near_withdraw(&mut self, amount: U128) -> Promise {
+        assert_one_yocto();
+        assert!(!self.soulbound, "token is soulbound");
+        self.assert_not_paused();
+        let account_id = env::predecessor_account_id();
+        self.token.internal_withdraw(&account_id, amount.into());
+        FtBurn {
+            owner_id: &account_id,
+            amount: &amount,
+            memo: Some("near_withdraw"),
+        }
+        .emit();
+        Promise::new(account_id).transfer(amount.into())
+    }
+
+    /// Issues a soulbound badge: registers `account_id` if needed, mints `amount` to it, and
+    /// marks it whitelisted. Works regardless of `soulbound`, but only matters as a credential
+    /// when the contract was created in soulbound mode, since transfers are blocked there.
+    pub fn mint(&mut self, account_id: AccountId, amount: U128) {
+        self.assert_owner();
+        if self.storage_balance_of(account_id.clone()).is_none() {
+            self.token.internal_register_account(&account_id);
+        }
+        self.token.internal_deposit(&account_id, amount.into());
+        self.factory_whitelist.insert(&account_id);
+        FtMint {
+            owner_id: &account_id,
+            amount: &amount,
+            memo: Some("soulbound badge minted"),
+        }
+        .emit();
+    }
+
+    /// Burns `account_id`'s entire balance and drops it from the whitelist, revoking its badge.
+    pub fn revoke(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        let balance = self.token.ft_balance_of(account_id.clone());
+        assert!(balance.0 > 0, "Account has no balance to revoke");
+        self.token.internal_withdraw(&account_id, balance.0);
+        self.factory_whitelist.remove(&account_id);
+        FtBurn {
+            owner_id: &account_id,
+            amount: &balance,
+            memo: Some("soulbound badge revoked"),
+        }
+        .emit();
+    }
+
+    /// Owner-only code upgrade. The new WASM is read straight out of `env::input()` instead of
+    /// a `Vec<u8>` argument so we don't pay to deserialize a multi-hundred-KB blob. Deploys the
+    /// code and schedules `migrate()` in the same promise batch, handing it all remaining gas
+    /// except a `GAS_FOR_MIGRATE_CALL` reserve.
+    pub fn upgrade(&self) -> Promise {
+        self.assert_owner();
+        let code = env::input().expect("Error: No input for upgrade").to_vec();
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call(
+                "migrate".to_string(),
+                Vec::new(),
+                NO_DEPOSIT,
+                env::prepaid_gas()
+                    .0
+                    .saturating_sub(env::used_gas().0)
+                    .saturating_sub(GAS_FOR_MIGRATE_CALL.0)
+                    .into(),
+            )
+    }
+
+    /// Runs as part of the `upgrade()` batch against the already-upgraded code. Reads the old
+    /// `Contract` state with Borsh and gives `on_upgrade` a chance to adjust it before it's
+    /// written back, so owners can carry out one-off migration logic when the schema changes.
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "migrate can only be called as a self-call from upgrade()"
+        );
+        let mut contract: Contract =
+            env::state_read().expect("Error: contract is not initialized");
+        contract.on_upgrade();
+        contract
+    }
+}
+
+// Hand-rolled instead of `impl_fungible_token_core!` so `ft_transfer`/`ft_transfer_call` can
+// check `paused` before delegating to `self.token`.
+#[near_bindgen]
+impl FungibleTokenCore for Contract {
+    #[payable]
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        assert!(!self.soulbound, "token is soulbound");
+        self.assert_not_paused();
+        self.token.ft_transfer(receiver_id, amount, memo)
+    }
+
+    #[payable]
+    fn ft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        assert!(!self.soulbound, "token is soulbound");
+        self.assert_not_paused();
+        self.token.ft_transfer_call(receiver_id, amount, memo, msg)
+    }
+
+    fn ft_total_supply(&self) -> U128 {
+        self.token.ft_total_supply()
+    }
+
+    fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        self.token.ft_balance_of(account_id)
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenResolver for Contract {
+    #[private]
+    fn ft_resolve_transfer(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> U128 {
+        let (used_amount, burned_amount) =
+            self.token.internal_resolve_transfer(&sender_id, receiver_id, amount);
+        if burned_amount > 0 {
+            self.on_tokens_burned(sender_id, burned_amount);
+        }
+        used_amount.into()
+    }
 }
 
-near_contract_standards::impl_fungible_token_core!(Contract, token, on_tokens_burned);
 near_contract_standards::impl_fungible_token_storage!(Contract, token, on_account_closed);
 
 #[near_bindgen]
@@ -207,7 +633,7 @@ mod tests {
     fn test_new() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
-        let contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        let contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into(), false);
         testing_env!(context.is_view(true).build());
         assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
         assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY);
@@ -228,7 +654,7 @@ mod tests {
         // log!("1번 - {}", accounts(1)); // receiver // bob
 
         testing_env!(context.build());
-        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
         testing_env!(context
             .storage_usage(env::storage_usage())
             .attached_deposit(contract.storage_balance_bounds().min.into())
@@ -267,7 +693,7 @@ mod tests {
     fn test_transfer() {
         let mut context = get_context(accounts(2));
         testing_env!(context.build());
-        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into(), false);
         testing_env!(context
             .storage_usage(env::storage_usage())
             .attached_deposit(contract.storage_balance_bounds().min.into())
@@ -293,4 +719,373 @@ mod tests {
         assert_eq!(contract.ft_balance_of(accounts(2)).0, (TOTAL_SUPPLY - transfer_amount));
         assert_eq!(contract.ft_balance_of(accounts(1)).0, transfer_amount);
     }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can call this method")]
+    fn test_upgrade_requires_owner() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.upgrade();
+    }
+
+    #[test]
+    fn test_migrate_preserves_state() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+        contract.add_to_whitelist(accounts(1));
+        env::state_write(&contract);
+
+        testing_env!(context.build());
+        let migrated = Contract::migrate();
+        testing_env!(context.is_view(true).build());
+        assert_eq!(migrated.ft_total_supply().0, TOTAL_SUPPLY);
+        assert_eq!(migrated.ft_balance_of(accounts(0)).0, TOTAL_SUPPLY);
+        assert!(migrated.is_whitelisted(accounts(1)));
+        assert!(!migrated.is_whitelisted(accounts(2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "migrate can only be called as a self-call from upgrade()")]
+    fn test_migrate_requires_self_call() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+        env::state_write(&contract);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        Contract::migrate();
+    }
+
+    #[test]
+    fn test_owner_can_manage_roles() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+
+        assert!(!contract.acl_has_role(PAUSER, accounts(1)));
+        contract.acl_grant_role(PAUSER, accounts(1));
+        assert!(contract.acl_has_role(PAUSER, accounts(1)));
+        contract.acl_revoke_role(PAUSER, accounts(1));
+        assert!(!contract.acl_has_role(PAUSER, accounts(1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient permissions")]
+    fn test_non_pauser_cannot_pause() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.pause();
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_paused_contract_blocks_ft_transfer() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+        contract.pause();
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.storage_deposit(None, None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.ft_transfer(accounts(1), (TOTAL_SUPPLY / 2).into(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_paused_contract_blocks_near_withdraw() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.storage_deposit(None, None);
+
+        let deposit_amount = 1_000_000_000_000_000_000_000_000;
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(deposit_amount)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.near_deposit();
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.pause();
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.near_withdraw(deposit_amount.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient permissions")]
+    fn test_non_manager_cannot_whitelist() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.add_to_whitelist(accounts(2));
+    }
+
+    #[test]
+    fn test_near_deposit_and_withdraw() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.storage_deposit(None, None);
+
+        let deposit_amount = 10_000_000_000_000_000_000_000_000; // 10 NEAR
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(deposit_amount)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.near_deposit();
+
+        testing_env!(context.is_view(true).attached_deposit(0).build());
+        assert_eq!(
+            contract.ft_total_supply().0,
+            TOTAL_SUPPLY + deposit_amount
+        );
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, deposit_amount);
+
+        testing_env!(context
+            .is_view(false)
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.near_withdraw(deposit_amount.into());
+
+        testing_env!(context.is_view(true).attached_deposit(0).build());
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 0);
+    }
+
+    #[test]
+    fn test_near_deposit_auto_registers_caller() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+
+        let storage_cost = contract.storage_balance_bounds().min.0;
+        let deposit_amount = 10_000_000_000_000_000_000_000_000; // 10 NEAR
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(storage_cost + deposit_amount)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.near_deposit();
+
+        testing_env!(context.is_view(true).attached_deposit(0).build());
+        assert!(contract.storage_balance_of(accounts(1)).is_some());
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, deposit_amount);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + deposit_amount);
+    }
+
+    #[test]
+    fn test_transfer_with_reference_splits_fee() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+
+        for account in [accounts(1), accounts(2)] {
+            testing_env!(context
+                .storage_usage(env::storage_usage())
+                .attached_deposit(contract.storage_balance_bounds().min.into())
+                .predecessor_account_id(account)
+                .build());
+            contract.storage_deposit(None, None);
+        }
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(0))
+            .build());
+        let amount = 1_000;
+        let fee_amount = 10;
+        let reference = "a".repeat(PAYMENT_REFERENCE_LENGTH);
+        contract.transfer_with_reference(
+            accounts(1),
+            amount.into(),
+            reference,
+            fee_amount.into(),
+            accounts(2),
+        );
+
+        testing_env!(context.is_view(true).build());
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, amount);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, fee_amount);
+        assert_eq!(
+            contract.ft_balance_of(accounts(0)).0,
+            TOTAL_SUPPLY - amount - fee_amount
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "payment_reference must be a 64-character hex string")]
+    fn test_transfer_with_reference_rejects_bad_reference() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+        contract.transfer_with_reference(accounts(1), 1.into(), "short".to_string(), 0.into(), accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "token is soulbound")]
+    fn test_soulbound_blocks_transfer_with_reference() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), true);
+        let reference = "a".repeat(PAYMENT_REFERENCE_LENGTH);
+        contract.transfer_with_reference(accounts(1), 1.into(), reference, 0.into(), accounts(2));
+    }
+
+    #[test]
+    fn test_transfer_batch_creates_one_receipt_per_transfer() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+
+        contract.transfer_batch(vec![
+            (accounts(1), 1_000.into()),
+            (accounts(2), 2_000.into()),
+        ]);
+
+        assert_eq!(get_created_receipts().len(), 3); // 2 ft_transfer legs + resolve_batch callback
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_paused_contract_blocks_transfer_batch() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+        contract.pause();
+        contract.transfer_batch(vec![(accounts(1), 1_000.into())]);
+    }
+
+    #[test]
+    #[should_panic(expected = "transfer_batch supports at most 15 transfers per call")]
+    fn test_transfer_batch_rejects_oversized_batch() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+        let transfers: Vec<(AccountId, U128)> =
+            (0..(MAX_TRANSFER_BATCH_SIZE + 1)).map(|_| (accounts(1), 1.into())).collect();
+        contract.transfer_batch(transfers);
+    }
+
+    #[test]
+    fn test_resolve_batch_whitelists_only_successful_receivers() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), false);
+
+        testing_env!(
+            context.build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            std::collections::HashMap::default(),
+            vec![PromiseResult::Successful(vec![]), PromiseResult::Failed]
+        );
+
+        let results = contract.resolve_batch(vec![accounts(1), accounts(2)]);
+
+        assert_eq!(results, vec![true, false]);
+        assert!(contract.is_whitelisted(accounts(1)));
+        assert!(!contract.is_whitelisted(accounts(2)));
+    }
+
+    #[test]
+    fn test_soulbound_mint_and_revoke() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), true);
+        assert!(contract.is_whitelisted(accounts(0)));
+
+        contract.mint(accounts(1), 500.into());
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 500);
+        assert!(contract.is_whitelisted(accounts(1)));
+
+        contract.revoke(accounts(1));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 0);
+        assert!(!contract.is_whitelisted(accounts(1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "token is soulbound")]
+    fn test_soulbound_blocks_ft_transfer() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), true);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.storage_deposit(None, None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.ft_transfer(accounts(1), 1.into(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "token is soulbound")]
+    fn test_soulbound_blocks_near_deposit() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into(), true);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.storage_deposit(None, None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1_000_000_000_000_000_000_000_000)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.near_deposit();
+    }
 }